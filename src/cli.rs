@@ -1,3 +1,4 @@
+use std::fmt;
 use std::path::PathBuf;
 
 #[cfg(not_in_build_rs)]
@@ -8,6 +9,11 @@ use u64 as ByteSize;
 #[derive(Debug, clap::Parser)]
 #[command(about, version = option_env!("CFG_RELEASE").unwrap_or(env!("CARGO_PKG_VERSION")))]
 pub struct Cli {
+    /// Don't cross filesystem boundaries; skip subtrees that aren't on the
+    /// same device as the root being traversed.
+    #[arg(long)]
+    pub one_file_system: bool,
+
     /// Include all zero-length files.
     #[arg(long)]
     pub include_empty: bool,
@@ -27,15 +33,95 @@ pub struct Cli {
     #[arg(long, short = 'r', default_values = ["4KiB", "64KiB", "1MiB"])]
     pub quantile_of: Vec<ByteSize>,
 
+    /// Record actually-allocated disk usage instead of apparent file size.
+    /// Falls back to apparent size on platforms where `st_blocks` is
+    /// unavailable.
+    #[arg(long, short = 's')]
+    pub disk_usage: bool,
+
+    /// Deduplicate hard-linked files so each inode is only counted once.
+    /// Unsupported on non-Unix platforms.
+    #[arg(long)]
+    pub dedup_links: bool,
+
+    /// Skip files and directories excluded by `.gitignore`, `.ignore`, and
+    /// the user's global git excludes.
+    #[arg(long)]
+    pub gitignore: bool,
+
+    /// Skip hidden files and directories (those whose name starts with `.`).
+    #[arg(long)]
+    pub hidden: bool,
+
+    /// Split the histogram into a separate section per group.
+    #[arg(long, value_enum)]
+    pub group_by: Option<GroupBy>,
+
+    /// How to render byte sizes in the text output. `binary` uses
+    /// KiB/MiB/GiB (powers of 1024), `si` uses KB/MB/GB (powers of 1000),
+    /// and `raw` prints exact byte counts with no suffix. Ignored by `--json`,
+    /// which always emits raw integer bytes.
+    #[arg(long, value_enum, default_value_t = Unit::Binary)]
+    pub unit: Unit,
+
     /// Print output in JSON format.
     #[arg(long)]
     pub json: bool,
 
-    /// The root directory to traverse.
-    pub root_path: PathBuf,
+    /// After traversal, save the aggregate histogram to this file in HDR's
+    /// compressed V2 log format, for comparison via `--diff` in a later run.
+    /// Not supported together with `--group-by`.
+    #[arg(long, conflicts_with = "group_by")]
+    pub save: Option<PathBuf>,
+
+    /// Load a histogram previously written by `--save` and report on it
+    /// directly, skipping traversal entirely. No root paths are needed.
+    /// Not supported together with `--group-by`.
+    #[arg(long, conflicts_with_all = ["diff", "group_by"])]
+    pub load: Option<PathBuf>,
+
+    /// Load a baseline histogram previously written by `--save`, traverse
+    /// the given roots as usual, and print how count, mean, and each
+    /// requested quantile have shifted since the baseline was captured.
+    /// The delta is always printed as text; `--json` does not apply to it.
+    /// Not supported together with `--group-by`.
+    #[arg(long, conflicts_with_all = ["load", "group_by"])]
+    pub diff: Option<PathBuf>,
+
+    /// The root directories to traverse. Not needed with `--load`.
+    pub root_paths: Vec<PathBuf>,
 
     /// The maximal concurrency. If set to zero, the effective value is
     /// twice the number of logical CPUs.
     #[arg(long, default_value = "0")]
     pub threads: usize,
 }
+
+/// The classifier used to split a histogram into per-group sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// Group by the lowercased file extension, with a catch-all bucket for
+    /// files without one.
+    Extension,
+}
+
+/// How byte sizes are rendered in the text output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Unit {
+    /// KiB/MiB/GiB, powers of 1024.
+    Binary,
+    /// KB/MB/GB, powers of 1000.
+    Si,
+    /// Exact byte counts with no suffix.
+    Raw,
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Binary => f.write_str("binary"),
+            Self::Si => f.write_str("si"),
+            Self::Raw => f.write_str("raw"),
+        }
+    }
+}
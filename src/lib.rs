@@ -1,8 +1,10 @@
 //! WARNING: The library interface of this crate is considered unstable and
 //! should not be relied on. The crate version is solely coresponding to the binary CLI.
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroUsize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use hdrhistogram::sync::Recorder;
@@ -11,9 +13,47 @@ use hdrhistogram::{Histogram, SyncHistogram};
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 
+/// A bucket label used by [`dir_size_histogram_grouped`] to split the
+/// resulting histograms. The default classifier, [`classify_by_extension`],
+/// uses the lowercased file extension, with the empty string as a catch-all
+/// bucket for files without one.
+pub type GroupKey = String;
+
+/// The default [`dir_size_histogram_grouped`] classifier: groups files by
+/// their lowercased extension. Files without an extension all fall into the
+/// same catch-all bucket, keyed by the empty string.
+pub fn classify_by_extension(path: &Path) -> GroupKey {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// The return type of [`dir_size_histograms`]: the aggregate histogram over
+/// all roots, plus one histogram per root.
+pub type MultiRootHistograms = (SyncHistogram<u64>, BTreeMap<PathBuf, SyncHistogram<u64>>);
+
+/// A boxed [`traverse_dir`] recording sink, owning whatever state (e.g. a
+/// captured root path) it needs to route a recorded file size.
+type RecordSink = Box<dyn Fn(&Path, u64) + Sync>;
+
 pub struct Config<'a> {
     pub one_file_system: bool,
     pub include_empty: bool,
+    /// Record actually-allocated disk usage (`blocks * 512`) instead of the
+    /// apparent file size. On non-Unix platforms, where there is no portable
+    /// way to query allocated blocks, this falls back to the apparent size.
+    pub disk_usage: bool,
+    /// Deduplicate hard-linked files so that each `(dev, ino)` is only
+    /// recorded once, regardless of how many directory entries link to it.
+    /// Unsupported on non-Unix platforms.
+    pub dedup_hardlinks: bool,
+    /// Skip files and directories excluded by `.gitignore`, `.ignore`, and
+    /// the user's global git excludes. Each directory's own files are read
+    /// while descending; `.git/info/exclude` is not consulted.
+    pub gitignore: bool,
+    /// Skip hidden files and directories (those whose name starts with `.`).
+    pub hidden: bool,
     pub threads: NonZeroUsize,
     pub on_error: &'a (dyn Fn(&Path, std::io::Error) + Sync),
 }
@@ -27,7 +67,287 @@ pub struct Config<'a> {
 pub fn dir_size_histogram(root_path: &Path, config: &Config<'_>) -> Result<SyncHistogram<u64>, ()> {
     let emit = |err| (config.on_error)(root_path, err);
 
-    let expect_dev_id = config
+    let expect_dev_id = expect_dev_id(root_path, config).map_err(emit)?;
+    let seen_inodes = seen_inodes(config).map_err(emit)?;
+    let ignore_stack = root_ignore_stack(root_path, config);
+
+    let record = |_path: &Path, size: u64| {
+        LOCAL_RECORDER.with(|recorder| {
+            recorder
+                .borrow_mut()
+                .record(size)
+                .expect("auto-resize is enabled");
+        });
+    };
+
+    let mut hist = Histogram::new(3).expect("sigfig 3 is valid").into_sync();
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads.get())
+        .build_scoped(
+            |thread| {
+                let recorder = RefCell::new(hist.recorder());
+                LOCAL_RECORDER.set(&recorder, || thread.run());
+            },
+            |pool| {
+                pool.scope(|s| {
+                    traverse_dir(
+                        s,
+                        root_path,
+                        config,
+                        expect_dev_id,
+                        seen_inodes.as_ref(),
+                        ignore_stack,
+                        &record,
+                    )
+                })
+            },
+        )
+        .expect("failed to build rayon runtime");
+    // All recorders should already died.
+    hist.refresh_timeout(Duration::ZERO);
+    Ok(hist)
+}
+
+/// Like [`dir_size_histogram`], but splits the recorded sizes into separate
+/// histograms by `classify`, returning one [`Histogram`] per observed
+/// [`GroupKey`].
+///
+/// # Error
+/// Errors are reported via `Config::on_error`. In case of critical errors, it returns `Err(())`.
+/// Otherwise, errors are reported and relevant files are skipped.
+#[allow(clippy::result_unit_err)]
+pub fn dir_size_histogram_grouped(
+    root_path: &Path,
+    config: &Config<'_>,
+    classify: &(dyn Fn(&Path) -> GroupKey + Sync),
+) -> Result<BTreeMap<GroupKey, Histogram<u64>>, ()> {
+    let emit = |err| (config.on_error)(root_path, err);
+
+    let expect_dev_id = expect_dev_id(root_path, config).map_err(emit)?;
+    let seen_inodes = seen_inodes(config).map_err(emit)?;
+    let ignore_stack = root_ignore_stack(root_path, config);
+
+    let record = |path: &Path, size: u64| {
+        let key = classify(path);
+        LOCAL_GROUPED.with(|groups| {
+            groups
+                .borrow_mut()
+                .entry(key)
+                .or_insert_with(|| Histogram::new(3).expect("sigfig 3 is valid"))
+                .record(size)
+                .expect("auto-resize is enabled");
+        });
+    };
+
+    let thread_locals: Mutex<Vec<HashMap<GroupKey, Histogram<u64>>>> = Mutex::new(Vec::new());
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads.get())
+        .build_scoped(
+            |thread| {
+                let local = RefCell::new(HashMap::new());
+                LOCAL_GROUPED.set(&local, || thread.run());
+                thread_locals
+                    .lock()
+                    .expect("thread_locals mutex is not poisoned")
+                    .push(local.into_inner());
+            },
+            |pool| {
+                pool.scope(|s| {
+                    traverse_dir(
+                        s,
+                        root_path,
+                        config,
+                        expect_dev_id,
+                        seen_inodes.as_ref(),
+                        ignore_stack,
+                        &record,
+                    )
+                })
+            },
+        )
+        .expect("failed to build rayon runtime");
+
+    let mut merged = BTreeMap::new();
+    for local in thread_locals
+        .into_inner()
+        .expect("thread_locals mutex is not poisoned")
+    {
+        for (key, hist) in local {
+            merged
+                .entry(key)
+                .or_insert_with(|| Histogram::new(3).expect("sigfig 3 is valid"))
+                .add(&hist)
+                .expect("histograms share the same sigfig and range");
+        }
+    }
+    Ok(merged)
+}
+
+/// Traverse multiple root directories, returning both a combined histogram
+/// over all of them and a histogram per root. All roots are traversed
+/// through the same rayon pool and hard-link dedup set, rather than spinning
+/// up a fresh pool per root.
+///
+/// `one_file_system` is applied independently to each root, since each root
+/// may live on a different device.
+///
+/// # Error
+/// Errors are reported via `Config::on_error`. In case of critical errors, it returns `Err(())`.
+/// Otherwise, errors are reported and relevant files are skipped.
+#[allow(clippy::result_unit_err)]
+pub fn dir_size_histograms(
+    root_paths: &[PathBuf],
+    config: &Config<'_>,
+) -> Result<MultiRootHistograms, ()> {
+    let Some((first_root, _)) = root_paths.split_first() else {
+        return Ok((
+            Histogram::new(3).expect("sigfig 3 is valid").into_sync(),
+            BTreeMap::new(),
+        ));
+    };
+    let seen_inodes = seen_inodes(config).map_err(|err| (config.on_error)(first_root, err))?;
+
+    let records: Vec<RecordSink> = root_paths
+        .iter()
+        .map(|root_path| {
+            let root_path = root_path.clone();
+            Box::new(move |_path: &Path, size: u64| {
+                LOCAL_MULTI_ROOT.with(|roots| {
+                    roots
+                        .borrow_mut()
+                        .entry(root_path.clone())
+                        .or_insert_with(|| Histogram::new(3).expect("sigfig 3 is valid"))
+                        .record(size)
+                        .expect("auto-resize is enabled");
+                });
+            }) as RecordSink
+        })
+        .collect();
+
+    let thread_locals: Mutex<Vec<HashMap<PathBuf, Histogram<u64>>>> = Mutex::new(Vec::new());
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads.get())
+        .build_scoped(
+            |thread| {
+                let local = RefCell::new(HashMap::new());
+                LOCAL_MULTI_ROOT.set(&local, || thread.run());
+                thread_locals
+                    .lock()
+                    .expect("thread_locals mutex is not poisoned")
+                    .push(local.into_inner());
+            },
+            |pool| {
+                pool.scope(|s| {
+                    for (root_path, record) in root_paths.iter().zip(&records) {
+                        let emit = |err| (config.on_error)(root_path, err);
+                        let Ok(expect_dev_id) = expect_dev_id(root_path, config).map_err(emit)
+                        else {
+                            continue;
+                        };
+                        let ignore_stack = root_ignore_stack(root_path, config);
+                        traverse_dir(
+                            s,
+                            root_path,
+                            config,
+                            expect_dev_id,
+                            seen_inodes.as_ref(),
+                            ignore_stack,
+                            record.as_ref(),
+                        );
+                    }
+                })
+            },
+        )
+        .expect("failed to build rayon runtime");
+
+    let mut per_root: BTreeMap<PathBuf, Histogram<u64>> = root_paths
+        .iter()
+        .map(|root_path| {
+            (
+                root_path.clone(),
+                Histogram::new(3).expect("sigfig 3 is valid"),
+            )
+        })
+        .collect();
+    for local in thread_locals
+        .into_inner()
+        .expect("thread_locals mutex is not poisoned")
+    {
+        for (root_path, hist) in local {
+            per_root
+                .entry(root_path)
+                .or_insert_with(|| Histogram::new(3).expect("sigfig 3 is valid"))
+                .add(&hist)
+                .expect("histograms share the same sigfig and range");
+        }
+    }
+
+    let mut merged = Histogram::new(3).expect("sigfig 3 is valid");
+    for hist in per_root.values() {
+        merged
+            .add(hist)
+            .expect("histograms share the same sigfig and range");
+    }
+
+    let per_root = per_root
+        .into_iter()
+        .map(|(root_path, hist)| (root_path, hist.into_sync()))
+        .collect();
+    Ok((merged.into_sync(), per_root))
+}
+
+/// Like [`dir_size_histograms`], but grouped via `classify` as in
+/// [`dir_size_histogram_grouped`]. All roots are merged into a single set of
+/// per-group histograms.
+///
+/// # Error
+/// Errors are reported via `Config::on_error`. In case of critical errors, it returns `Err(())`.
+/// Otherwise, errors are reported and relevant files are skipped.
+#[allow(clippy::result_unit_err)]
+pub fn dir_size_histograms_grouped(
+    root_paths: &[PathBuf],
+    config: &Config<'_>,
+    classify: &(dyn Fn(&Path) -> GroupKey + Sync),
+) -> Result<BTreeMap<GroupKey, Histogram<u64>>, ()> {
+    let mut merged: BTreeMap<GroupKey, Histogram<u64>> = BTreeMap::new();
+    for root_path in root_paths {
+        let groups = dir_size_histogram_grouped(root_path, config, classify)?;
+        for (key, hist) in groups {
+            merged
+                .entry(key)
+                .or_insert_with(|| Histogram::new(3).expect("sigfig 3 is valid"))
+                .add(&hist)
+                .expect("histograms share the same sigfig and range");
+        }
+    }
+    Ok(merged)
+}
+
+/// Serialize `hist` to `path` in HDR's compressed V2 log format, for later
+/// comparison via [`load_histogram`].
+pub fn save_histogram(hist: &Histogram<u64>, path: &Path) -> std::io::Result<()> {
+    use hdrhistogram::serialization::{Serializer, V2DeflateSerializer};
+
+    let mut file = std::fs::File::create(path)?;
+    V2DeflateSerializer::new()
+        .serialize(hist, &mut file)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    Ok(())
+}
+
+/// Deserialize a histogram previously written by [`save_histogram`].
+pub fn load_histogram(path: &Path) -> std::io::Result<Histogram<u64>> {
+    use hdrhistogram::serialization::Deserializer;
+
+    let mut file = std::fs::File::open(path)?;
+    Deserializer::new()
+        .deserialize(&mut file)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+}
+
+/// Compute the device id that `one_file_system` filtering should stay within, if enabled.
+fn expect_dev_id(root_path: &Path, config: &Config<'_>) -> Result<Option<u64>, std::io::Error> {
+    config
         .one_file_system
         .then(|| {
             #[cfg(unix)]
@@ -44,31 +364,157 @@ pub fn dir_size_histogram(root_path: &Path, config: &Config<'_>) -> Result<SyncH
             }
         })
         .transpose()
-        .map_err(emit)?;
+}
 
-    let mut hist = Histogram::new(3).expect("sigfig 3 is valid").into_sync();
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(config.threads.get())
-        .build_scoped(
-            |thread| {
-                let recorder = RefCell::new(hist.recorder());
-                LOCAL_RECORDER.set(&recorder, || thread.run());
-            },
-            |pool| pool.scope(|s| traverse_dir(s, root_path, config, expect_dev_id)),
-        )
-        .expect("failed to build rayon runtime");
-    // All recorders should already died.
-    hist.refresh_timeout(Duration::ZERO);
-    Ok(hist)
+/// One level of a `.gitignore` "stack": the compiled matcher for a single
+/// directory, chained to its parent directory's level so that patterns
+/// declared higher up still apply to its descendants. The root of the chain
+/// holds the user's global git excludes.
+struct IgnoreLevel {
+    matcher: ignore::gitignore::Gitignore,
+    parent: Option<Arc<IgnoreLevel>>,
+}
+
+impl IgnoreLevel {
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        match self.matcher.matched(path, is_dir) {
+            ignore::Match::Ignore(_) => true,
+            ignore::Match::Whitelist(_) => false,
+            ignore::Match::None => self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_ignored(path, is_dir)),
+        }
+    }
+}
+
+/// Compile `dir`'s own `.gitignore` and `.ignore` into a new level on top of `parent`.
+fn child_ignore_stack(dir: &Path, parent: Option<Arc<IgnoreLevel>>) -> Arc<IgnoreLevel> {
+    let mut builder = ignore::gitignore::GitignoreBuilder::new(dir);
+    builder.add(dir.join(".gitignore"));
+    builder.add(dir.join(".ignore"));
+    let matcher = builder
+        .build()
+        .unwrap_or_else(|_| ignore::gitignore::Gitignore::empty());
+    Arc::new(IgnoreLevel { matcher, parent })
+}
+
+/// Build the initial ignore stack for `root_path`, seeded with the user's
+/// global git excludes, if `Config::gitignore` is enabled.
+fn root_ignore_stack(root_path: &Path, config: &Config<'_>) -> Option<Arc<IgnoreLevel>> {
+    config.gitignore.then(|| {
+        let (matcher, _err) = ignore::gitignore::Gitignore::global();
+        let global = Arc::new(IgnoreLevel {
+            matcher,
+            parent: None,
+        });
+        child_ignore_stack(root_path, Some(global))
+    })
+}
+
+/// Whether a directory entry's own file name marks it as hidden.
+fn is_hidden(ent: &std::fs::DirEntry) -> bool {
+    ent.file_name()
+        .to_str()
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// Build the shared `(dev, ino)` set used to deduplicate hard links, if enabled.
+fn seen_inodes(
+    config: &Config<'_>,
+) -> Result<Option<dashmap::DashSet<(u64, u64)>>, std::io::Error> {
+    config
+        .dedup_hardlinks
+        .then(|| {
+            #[cfg(unix)]
+            {
+                Ok(dashmap::DashSet::<(u64, u64)>::new())
+            }
+
+            #[cfg(not(unix))]
+            {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "hard-link deduplication is unsupported on this platform",
+                ))
+            }
+        })
+        .transpose()
 }
 
 scoped_tls::scoped_thread_local!(static LOCAL_RECORDER: RefCell<Recorder<u64>>);
+scoped_tls::scoped_thread_local!(static LOCAL_GROUPED: RefCell<HashMap<GroupKey, Histogram<u64>>>);
+scoped_tls::scoped_thread_local!(static LOCAL_MULTI_ROOT: RefCell<HashMap<PathBuf, Histogram<u64>>>);
 
+/// Compute the size to record for a file, either the apparent size or, if
+/// `disk_usage` is requested, the actually-allocated size in bytes.
+fn file_size(meta: &std::fs::Metadata, disk_usage: bool) -> u64 {
+    #[cfg(unix)]
+    if disk_usage {
+        return meta.blocks() * 512;
+    }
+    #[cfg(not(unix))]
+    let _ = disk_usage;
+    meta.len()
+}
+
+/// Resolve the file type of a directory entry, along with its metadata if it
+/// was already fetched as a side effect of `one_file_system` filtering.
+/// Returns `Ok(None)` if the entry should be skipped (filtered out by
+/// `expect_dev_id`).
+fn resolve_entry(
+    ent: &std::fs::DirEntry,
+    expect_dev_id: Option<u64>,
+) -> std::io::Result<Option<(std::fs::FileType, Option<std::fs::Metadata>)>> {
+    if let Some(expect_dev_id) = expect_dev_id {
+        #[cfg(unix)]
+        {
+            let meta = ent.metadata()?;
+            if meta.dev() != expect_dev_id {
+                return Ok(None);
+            }
+            Ok(Some((meta.file_type(), Some(meta))))
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = expect_dev_id;
+            unreachable!()
+        }
+    } else {
+        Ok(Some((ent.file_type()?, None)))
+    }
+}
+
+/// Whether `meta` is a repeat observation of an already-seen hard link that
+/// should be skipped.
+fn is_duplicate_hardlink(
+    #[cfg_attr(not(unix), allow(unused_variables))] meta: &std::fs::Metadata,
+    seen_inodes: Option<&dashmap::DashSet<(u64, u64)>>,
+) -> bool {
+    #[cfg(unix)]
+    if let Some(seen_inodes) = seen_inodes {
+        return meta.nlink() > 1 && !seen_inodes.insert((meta.dev(), meta.ino()));
+    }
+    #[cfg(not(unix))]
+    let _ = seen_inodes;
+    false
+}
+
+/// Recursively traverse `path`, calling `record(file_path, size)` for each
+/// file that passes the `one_file_system`/hidden/gitignore/hardlink-dedup
+/// filters in `config`. Shared by every histogram-gathering entry point
+/// (single-group, per-group, single-root, multi-root); callers differ only
+/// in how `record` stores what it's given.
+#[allow(clippy::too_many_arguments)]
 fn traverse_dir<'s>(
     s: &rayon::Scope<'s>,
     path: &Path,
     config: &'s Config<'s>,
     expect_dev_id: Option<u64>,
+    seen_inodes: Option<&'s dashmap::DashSet<(u64, u64)>>,
+    ignore_stack: Option<Arc<IgnoreLevel>>,
+    record: &'s (dyn Fn(&Path, u64) + Sync),
 ) {
     let emit = |err| (config.on_error)(path, err);
     let Ok(iter) = std::fs::read_dir(path).map_err(emit) else {
@@ -79,44 +525,50 @@ fn traverse_dir<'s>(
         let Ok(ent) = ent.map_err(emit) else {
             continue;
         };
+        if config.hidden && is_hidden(&ent) {
+            continue;
+        }
+        let ignore_stack = ignore_stack.clone();
         s.spawn(move |s| {
             let ret = (|| {
-                let (file_type, meta) = if let Some(expect_dev_id) = expect_dev_id {
-                    #[cfg(unix)]
-                    {
-                        let meta = ent.metadata()?;
-                        if meta.dev() != expect_dev_id {
-                            return Ok(());
-                        }
-                        (meta.file_type(), Some(meta))
-                    }
-
-                    #[cfg(not(unix))]
-                    {
-                        let _ = expect_dev_id;
-                        unreachable!()
-                    }
-                } else {
-                    (ent.file_type()?, None::<std::fs::Metadata>)
+                let Some((file_type, meta)) = resolve_entry(&ent, expect_dev_id)? else {
+                    return Ok(());
                 };
+                if let Some(stack) = &ignore_stack {
+                    if stack.is_ignored(&ent.path(), file_type.is_dir()) {
+                        return Ok(());
+                    }
+                }
 
                 if !file_type.is_dir() {
-                    let size = match meta {
-                        Some(meta) => meta.len(),
-                        None => ent.metadata()?.len(),
+                    let meta = match meta {
+                        Some(meta) => meta,
+                        None => ent.metadata()?,
                     };
+                    if is_duplicate_hardlink(&meta, seen_inodes) {
+                        return Ok(());
+                    }
+
+                    let size = file_size(&meta, config.disk_usage);
                     if size == 0 && !config.include_empty {
                         return Ok(());
                     }
-                    LOCAL_RECORDER.with(|recorder| {
-                        recorder
-                            .borrow_mut()
-                            .record(size)
-                            .expect("auto-resize is enabled");
-                    });
+                    record(&ent.path(), size);
                 } else {
                     let file_path = ent.path();
-                    s.spawn(move |s| traverse_dir(s, &file_path, config, expect_dev_id));
+                    let child_stack =
+                        ignore_stack.map(|parent| child_ignore_stack(&file_path, Some(parent)));
+                    s.spawn(move |s| {
+                        traverse_dir(
+                            s,
+                            &file_path,
+                            config,
+                            expect_dev_id,
+                            seen_inodes,
+                            child_stack,
+                            record,
+                        )
+                    });
                 }
                 Ok(())
             })();
@@ -126,3 +578,170 @@ fn traverse_dir<'s>(
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An `on_error` that silently drops errors, for tests that don't expect any.
+    fn silent_on_error(_path: &Path, _err: std::io::Error) {}
+
+    #[test]
+    fn grouped_histograms_split_by_extension() {
+        let dir = std::env::temp_dir().join(format!("histodu-test-{}-grouped", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        std::fs::write(dir.join("a.txt"), b"hello").expect("write a.txt");
+        std::fs::write(dir.join("b.TXT"), b"world!").expect("write b.TXT");
+        std::fs::write(dir.join("c"), b"no-extension").expect("write c");
+
+        let config = Config {
+            one_file_system: false,
+            include_empty: false,
+            disk_usage: false,
+            dedup_hardlinks: false,
+            gitignore: false,
+            hidden: false,
+            threads: NonZeroUsize::new(1).expect("1 is not zero"),
+            on_error: &silent_on_error,
+        };
+        let groups =
+            dir_size_histogram_grouped(&dir, &config, &classify_by_extension).expect("traverse");
+
+        // `classify_by_extension` lowercases extensions, so `a.txt` and `b.TXT`
+        // land in the same "txt" group, while the extension-less `c` lands in
+        // the empty catch-all group.
+        assert_eq!(groups.get("txt").map(Histogram::len), Some(2));
+        assert_eq!(groups.get("").map(Histogram::len), Some(1));
+
+        std::fs::remove_dir_all(&dir).expect("clean up temp dir");
+    }
+
+    #[test]
+    fn multi_root_histograms_merge_per_root_and_aggregate() {
+        let base =
+            std::env::temp_dir().join(format!("histodu-test-{}-multiroot", std::process::id()));
+        let root_a = base.join("a");
+        let root_b = base.join("b");
+        std::fs::create_dir_all(&root_a).expect("create root_a");
+        std::fs::create_dir_all(&root_b).expect("create root_b");
+        std::fs::write(root_a.join("one"), b"12345").expect("write root_a/one");
+        std::fs::write(root_a.join("two"), b"1234567890").expect("write root_a/two");
+        std::fs::write(root_b.join("three"), b"123").expect("write root_b/three");
+
+        let config = Config {
+            one_file_system: false,
+            include_empty: false,
+            disk_usage: false,
+            dedup_hardlinks: false,
+            gitignore: false,
+            hidden: false,
+            threads: NonZeroUsize::new(2).expect("2 is not zero"),
+            on_error: &silent_on_error,
+        };
+        let (aggregate, per_root) =
+            dir_size_histograms(&[root_a.clone(), root_b.clone()], &config).expect("traverse");
+
+        assert_eq!(aggregate.len(), 3);
+        assert_eq!(per_root.get(&root_a).map(|hist| hist.len()), Some(2));
+        assert_eq!(per_root.get(&root_b).map(|hist| hist.len()), Some(1));
+
+        std::fs::remove_dir_all(&base).expect("clean up temp dir");
+    }
+
+    #[test]
+    fn traversal_skips_gitignored_and_hidden_entries() {
+        let dir = std::env::temp_dir().join(format!("histodu-test-{}-ignore", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        std::fs::write(dir.join(".gitignore"), b"*.log\n").expect("write .gitignore");
+        std::fs::write(dir.join("kept.txt"), b"kept").expect("write kept.txt");
+        std::fs::write(dir.join("ignored.log"), b"ignored by gitignore")
+            .expect("write ignored.log");
+        std::fs::write(dir.join(".hidden"), b"ignored as hidden").expect("write .hidden");
+
+        let config = Config {
+            one_file_system: false,
+            include_empty: false,
+            disk_usage: false,
+            dedup_hardlinks: false,
+            gitignore: true,
+            hidden: true,
+            threads: NonZeroUsize::new(1).expect("1 is not zero"),
+            on_error: &silent_on_error,
+        };
+        let hist = dir_size_histogram(&dir, &config).expect("traverse");
+
+        // Only `kept.txt` survives: `.gitignore` and `.hidden` are excluded by
+        // `hidden`, and `ignored.log` is excluded by the `*.log` gitignore rule.
+        assert_eq!(hist.len(), 1);
+
+        std::fs::remove_dir_all(&dir).expect("clean up temp dir");
+    }
+
+    #[test]
+    fn histogram_save_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("histodu-test-{}.hdr", std::process::id()));
+
+        let mut hist = Histogram::<u64>::new(3).expect("sigfig 3 is valid");
+        for size in [0, 1, 4096, 1 << 20, 1 << 30] {
+            hist.record(size).expect("auto-resize is enabled");
+        }
+        save_histogram(&hist, &path).expect("save histogram");
+        let loaded = load_histogram(&path).expect("load histogram");
+
+        assert_eq!(loaded.len(), hist.len());
+        assert_eq!(loaded.mean(), hist.mean());
+        for percent in [0, 50, 90, 99, 100] {
+            assert_eq!(
+                loaded.value_at_quantile(percent as f64 / 100.0),
+                hist.value_at_quantile(percent as f64 / 100.0)
+            );
+        }
+
+        std::fs::remove_file(&path).expect("clean up temp file");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_size_respects_disk_usage_flag() {
+        let dir =
+            std::env::temp_dir().join(format!("histodu-test-{}-file-size", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join("sparse");
+        {
+            use std::io::{Seek, SeekFrom, Write};
+            let mut file = std::fs::File::create(&path).expect("create file");
+            file.seek(SeekFrom::Start(1 << 20))
+                .expect("seek past a 1 MiB hole");
+            file.write_all(b"x").expect("write one byte past the hole");
+        }
+        let meta = path.metadata().expect("stat file");
+
+        assert_eq!(file_size(&meta, false), meta.len());
+        assert!(
+            file_size(&meta, true) < meta.len(),
+            "a sparse file's allocated blocks should be far smaller than its logical size"
+        );
+
+        std::fs::remove_dir_all(&dir).expect("clean up temp dir");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn duplicate_hardlink_detection() {
+        let dir = std::env::temp_dir().join(format!("histodu-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let original = dir.join("original");
+        let link = dir.join("link");
+        std::fs::write(&original, b"hello").expect("write original");
+        std::fs::hard_link(&original, &link).expect("create hard link");
+
+        let seen_inodes = dashmap::DashSet::new();
+        let original_meta = original.metadata().expect("stat original");
+        let link_meta = link.metadata().expect("stat link");
+
+        assert!(!is_duplicate_hardlink(&original_meta, Some(&seen_inodes)));
+        assert!(is_duplicate_hardlink(&link_meta, Some(&seen_inodes)));
+
+        std::fs::remove_dir_all(&dir).expect("clean up temp dir");
+    }
+}
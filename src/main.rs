@@ -1,42 +1,133 @@
 use std::collections::BTreeMap;
-use std::fmt;
 use std::num::NonZeroUsize;
 
 use bytesize::ByteSize;
 use clap::Parser;
+use hdrhistogram::Histogram;
 use miniserde::Serialize;
 
 mod cli;
 
 #[derive(Debug, Serialize)]
-struct Output {
+struct Stats {
     count: u64,
     mean: f64,
     at_quantile: BTreeMap<u8, u64>,
     quantile_of: BTreeMap<u64, f64>,
 }
 
-impl fmt::Display for Output {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "count = {}", self.count)?;
-        let bytes = |b| ByteSize(b).to_string_as(true);
-        writeln!(f, "mean = {}", bytes(self.mean as u64))?;
-        for (&percent, &sz) in &self.at_quantile {
-            writeln!(f, "{}% = {}", percent, ByteSize(sz))?;
+impl Stats {
+    fn from_histogram(hist: &Histogram<u64>, cli: &cli::Cli) -> Self {
+        Self {
+            count: hist.len(),
+            mean: hist.mean(),
+            at_quantile: cli
+                .at_quantile
+                .iter()
+                .map(|&percent| (percent, hist.value_at_quantile(percent as f64 / 100.0)))
+                .collect(),
+            quantile_of: cli
+                .quantile_of
+                .iter()
+                .map(|&size| size.as_u64())
+                .map(|bytes| (bytes, hist.quantile_below(bytes)))
+                .collect(),
         }
-        for (&size, &q) in &self.quantile_of {
-            writeln!(f, "{:.3}% = {}", q * 100.0, bytes(size))?;
-        }
-        Ok(())
     }
 }
 
+/// Render a byte count according to the chosen `--unit`.
+fn render_bytes(bytes: u64, unit: cli::Unit) -> String {
+    match unit {
+        cli::Unit::Binary => ByteSize(bytes).to_string_as(true),
+        cli::Unit::Si => ByteSize(bytes).to_string_as(false),
+        cli::Unit::Raw => bytes.to_string(),
+    }
+}
+
+fn print_stats(stat: &Stats, unit: cli::Unit) {
+    println!("count = {}", stat.count);
+    println!("mean = {}", render_bytes(stat.mean as u64, unit));
+    for (&percent, &sz) in &stat.at_quantile {
+        println!("{percent}% = {}", render_bytes(sz, unit));
+    }
+    for (&size, &q) in &stat.quantile_of {
+        println!("{:.3}% = {}", q * 100.0, render_bytes(size, unit));
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MultiRootReport {
+    roots: BTreeMap<String, Stats>,
+    aggregate: Stats,
+}
+
+/// Render the delta between a freshly scanned `fresh` histogram and a
+/// `--save`d `baseline`, for `--diff`.
+fn print_diff(baseline: &Stats, fresh: &Stats, unit: cli::Unit) {
+    let format_signed_bytes = |delta: i64| {
+        let sign = if delta >= 0 { "+" } else { "-" };
+        format!("{sign}{}", render_bytes(delta.unsigned_abs(), unit))
+    };
+
+    let count_delta = fresh.count as i64 - baseline.count as i64;
+    println!("count = {} ({count_delta:+})", fresh.count);
+    println!(
+        "mean = {} ({})",
+        render_bytes(fresh.mean as u64, unit),
+        format_signed_bytes(fresh.mean as i64 - baseline.mean as i64)
+    );
+    for (&percent, &sz) in &fresh.at_quantile {
+        let baseline_sz = baseline.at_quantile.get(&percent).copied().unwrap_or(0);
+        println!(
+            "{percent}% = {} ({})",
+            render_bytes(sz, unit),
+            format_signed_bytes(sz as i64 - baseline_sz as i64)
+        );
+    }
+    for (&size, &q) in &fresh.quantile_of {
+        let baseline_q = baseline.quantile_of.get(&size).copied().unwrap_or(0.0);
+        println!(
+            "{:.3}% = {} ({:+.3}pp)",
+            q * 100.0,
+            render_bytes(size, unit),
+            (q - baseline_q) * 100.0
+        );
+    }
+}
+
+/// The bucket label shown for [`histodu::classify_by_extension`]'s catch-all group.
+const NO_EXTENSION_LABEL: &str = "<no extension>";
+
 fn main() {
     let cli = cli::Cli::parse();
 
+    if let Some(load_path) = &cli.load {
+        let hist = histodu::load_histogram(load_path).unwrap_or_else(|err| {
+            eprintln!("{}: {err}", load_path.display());
+            std::process::exit(1);
+        });
+        let stat = Stats::from_histogram(&hist, &cli);
+        if cli.json {
+            println!("{}", miniserde::json::to_string(&stat));
+        } else {
+            print_stats(&stat, cli.unit);
+        }
+        return;
+    }
+
+    if cli.root_paths.is_empty() {
+        eprintln!("error: at least one root path is required unless --load is given");
+        std::process::exit(1);
+    }
+
     let config = histodu::Config {
         one_file_system: cli.one_file_system,
         include_empty: cli.include_empty,
+        disk_usage: cli.disk_usage,
+        dedup_hardlinks: cli.dedup_links,
+        gitignore: cli.gitignore,
+        hidden: cli.hidden,
         threads: NonZeroUsize::new(cli.threads).unwrap_or_else(|| {
             std::thread::available_parallelism()
                 .expect("failed to get available parallelism")
@@ -45,32 +136,106 @@ fn main() {
         on_error: &|path, err| eprintln!("{}: {}", path.display(), err),
     };
 
-    let hist = match histodu::dir_size_histogram(&cli.root_path, &config) {
-        Ok(hist) => hist,
-        // Errors should already be reported via `on_error`.
-        Err(()) => std::process::exit(1),
-    };
+    match cli.group_by {
+        Some(cli::GroupBy::Extension) => {
+            let groups = match histodu::dir_size_histograms_grouped(
+                &cli.root_paths,
+                &config,
+                &histodu::classify_by_extension,
+            ) {
+                Ok(groups) => groups,
+                // Errors should already be reported via `on_error`.
+                Err(()) => std::process::exit(1),
+            };
 
-    let out = Output {
-        count: hist.len(),
-        mean: hist.mean(),
-        at_quantile: cli
-            .at_quantile
-            .iter()
-            .map(|&percent| (percent, hist.value_at_quantile(percent as f64 / 100.0)))
-            .collect(),
-        quantile_of: cli
-            .quantile_of
-            .iter()
-            .map(|&size| size.as_u64())
-            .map(|bytes| (bytes, hist.quantile_below(bytes)))
-            .collect(),
-    };
+            let stats: BTreeMap<String, Stats> = groups
+                .iter()
+                .map(|(key, hist)| (key.clone(), Stats::from_histogram(hist, &cli)))
+                .collect();
+
+            if cli.json {
+                println!("{}", miniserde::json::to_string(&stats));
+            } else {
+                for (key, stat) in &stats {
+                    let label = if key.is_empty() {
+                        NO_EXTENSION_LABEL
+                    } else {
+                        key.as_str()
+                    };
+                    println!("[{label}]");
+                    print_stats(stat, cli.unit);
+                    println!();
+                }
+            }
+        }
+        None => {
+            let (merged, per_root) = match histodu::dir_size_histograms(&cli.root_paths, &config) {
+                Ok(result) => result,
+                // Errors should already be reported via `on_error`.
+                Err(()) => std::process::exit(1),
+            };
+            let aggregate = Stats::from_histogram(&merged, &cli);
+
+            if let Some(save_path) = &cli.save {
+                if let Err(err) = histodu::save_histogram(&merged, save_path) {
+                    eprintln!("{}: {err}", save_path.display());
+                    std::process::exit(1);
+                }
+            }
+
+            if let Some(diff_path) = &cli.diff {
+                let baseline_hist = histodu::load_histogram(diff_path).unwrap_or_else(|err| {
+                    eprintln!("{}: {err}", diff_path.display());
+                    std::process::exit(1);
+                });
+                let baseline = Stats::from_histogram(&baseline_hist, &cli);
+                print_diff(&baseline, &aggregate, cli.unit);
+                return;
+            }
+
+            if cli.root_paths.len() == 1 {
+                if cli.json {
+                    println!("{}", miniserde::json::to_string(&aggregate));
+                } else {
+                    print_stats(&aggregate, cli.unit);
+                }
+                return;
+            }
+
+            let roots: BTreeMap<String, Stats> = per_root
+                .iter()
+                .map(|(path, hist)| {
+                    (
+                        path.display().to_string(),
+                        Stats::from_histogram(hist, &cli),
+                    )
+                })
+                .collect();
+
+            if cli.json {
+                let report = MultiRootReport { roots, aggregate };
+                println!("{}", miniserde::json::to_string(&report));
+            } else {
+                for (path, stat) in &roots {
+                    println!("[{path}]");
+                    print_stats(stat, cli.unit);
+                    println!();
+                }
+                println!("[aggregate]");
+                print_stats(&aggregate, cli.unit);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if cli.json {
-        let out = miniserde::json::to_string(&out);
-        println!("{out}");
-    } else {
-        println!("{out}");
+    #[test]
+    fn render_bytes_scales_by_unit() {
+        assert_eq!(render_bytes(4096, cli::Unit::Binary), "4.0 kiB");
+        assert_eq!(render_bytes(4096, cli::Unit::Si), "4.1 KB");
+        assert_eq!(render_bytes(4096, cli::Unit::Raw), "4096");
     }
 }